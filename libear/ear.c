@@ -47,6 +47,8 @@
 #include <fcntl.h>
 #include <pthread.h>
 #include <errno.h>
+#include <sys/wait.h>
+#include <sys/resource.h>
 
 #if defined HAVE_POSIX_SPAWN || defined HAVE_POSIX_SPAWNP
 #include <spawn.h>
@@ -62,13 +64,18 @@ extern char **environ;
 // #include <libwutils.h>
 
 #define ENV_OUTPUT "INTERCEPT_BUILD_TARGET_DIR"
+#define ENV_REPORT_STATUS "INTERCEPT_BUILD_REPORT_STATUS"
+#define ENV_REPORT_EXIT "INTERCEPT_BUILD_REPORT_EXIT"
+#define ENV_REPORT_ENV "INTERCEPT_BUILD_REPORT_ENV"
 #ifdef APPLE
 # define ENV_FLAT    "DYLD_FORCE_FLAT_NAMESPACE"
 # define ENV_PRELOAD "DYLD_INSERT_LIBRARIES"
 # define ENV_SIZE 3
+# define INTERCEPTION_METHOD "dyld_insert_libraries"
 #else
 # define ENV_PRELOAD "LD_PRELOAD"
 # define ENV_SIZE 2
+# define INTERCEPTION_METHOD "ld_preload"
 #endif
 
 #define STRINGIFY(x) #x
@@ -97,10 +104,21 @@ static int capture_env_t(bear_env_t *env);
 static void release_env_t(bear_env_t *env);
 static char const **string_array_partial_update(char *const envp[], bear_env_t *env);
 static char const **string_array_single_update(char const *envs[], char const *key, char const *value);
-static void report_call(char const *const argv[]);
-static int write_report(int fd, char const *const argv[]);
+static void report_call(char const *path, char const *const argv[],
+                        char const *const envp[]);
+static void report_process(char const *path, pid_t pid, pid_t ppid,
+                           char const *const argv[], char const *const envp[]);
+static int write_report(int fd, char const *path, pid_t pid, pid_t ppid,
+                        char const *const argv[], char const *const envp[],
+                        struct timespec const *ts);
+static void report_exit(void);
+static void report_status(pid_t pid, int status);
+static void report_exit_code(pid_t pid, int exit_code);
+static int write_binary_pid(int fd, pid_t pid);
+static int write_binary_time(int fd, struct timespec const *ts);
 static char const **string_array_from_varargs(char const * arg, va_list *args);
 static char const **string_array_copy(char const **in);
+static char const **string_array_select(char const *const *in, char const *names);
 static size_t string_array_length(char const *const *in);
 static void string_array_release(char const **);
 
@@ -122,6 +140,9 @@ static bear_env_t initial_env =
     };
 
 static int initialized = 0;
+static int report_status_enabled = 0;
+static int report_exit_enabled = 0;
+static char const *report_env_names = 0;
 static pthread_mutex_t mutex = PTHREAD_MUTEX_INITIALIZER;
 
 static void on_load(void) __attribute__((constructor));
@@ -164,6 +185,22 @@ static int call_posix_spawnp(pid_t *restrict pid, const char *restrict file,
                              char *const argv[restrict],
                              char *const envp[restrict]);
 #endif
+#ifdef HAVE_WAIT
+static pid_t call_wait(int *status);
+#endif
+#ifdef HAVE_WAITPID
+static pid_t call_waitpid(pid_t pid, int *status, int options);
+#endif
+#ifdef HAVE_WAIT3
+static pid_t call_wait3(int *status, int options, struct rusage *rusage);
+#endif
+#ifdef HAVE_WAIT4
+static pid_t call_wait4(pid_t pid, int *status, int options,
+                        struct rusage *rusage);
+#endif
+#ifdef HAVE_WAITID
+static int call_waitid(idtype_t idtype, id_t id, siginfo_t *info, int options);
+#endif
 
 
 /* Initialization method to Captures the relevant environment variables.
@@ -190,12 +227,23 @@ static int mt_safe_on_load(void) {
     if (0 == environ)
         return 0;
 #endif
+    // The optional reports are requested by the driver only when needed
+    report_status_enabled = (0 != getenv(ENV_REPORT_STATUS));
+    report_exit_enabled = (0 != getenv(ENV_REPORT_EXIT));
+    char const * const names = getenv(ENV_REPORT_ENV);
+    if (names && (0 == (report_env_names = strdup(names)))) {
+        PERROR("strdup");
+        return 0;
+    }
     // Capture current relevant environment variables
     return capture_env_t(&initial_env);
 }
 
 static void mt_safe_on_unload(void) {
+    report_exit();
     release_env_t(&initial_env);
+    free((void *)report_env_names);
+    report_env_names = 0;
 }
 
 
@@ -204,7 +252,8 @@ static void mt_safe_on_unload(void) {
 
 #ifdef HAVE_EXECVE
 int execve(const char *path, char *const argv[], char *const envp[]) {
-    report_call((char const *const *)argv);
+    report_call(path, (char const *const *)argv,
+                (char const *const *)envp);
     return call_execve(path, argv, envp);
 }
 #endif
@@ -214,35 +263,40 @@ int execve(const char *path, char *const argv[], char *const envp[]) {
 #error can not implement execv without execve
 #endif
 int execv(const char *path, char *const argv[]) {
-    report_call((char const *const *)argv);
+    report_call(path, (char const *const *)argv,
+                (char const *const *)environ);
     return call_execve(path, argv, environ);
 }
 #endif
 
 #ifdef HAVE_EXECVPE
 int execvpe(const char *file, char *const argv[], char *const envp[]) {
-    report_call((char const *const *)argv);
+    report_call(file, (char const *const *)argv,
+                (char const *const *)envp);
     return call_execvpe(file, argv, envp);
 }
 #endif
 
 #ifdef HAVE_EXECVP
 int execvp(const char *file, char *const argv[]) {
-    report_call((char const *const *)argv);
+    report_call(file, (char const *const *)argv,
+                (char const *const *)environ);
     return call_execvp(file, argv);
 }
 #endif
 
 #ifdef HAVE_EXECVP2
 int execvP(const char *file, const char *search_path, char *const argv[]) {
-    report_call((char const *const *)argv);
+    report_call(file, (char const *const *)argv,
+                (char const *const *)environ);
     return call_execvP(file, search_path, argv);
 }
 #endif
 
 #ifdef HAVE_EXECT
 int exect(const char *path, char *const argv[], char *const envp[]) {
-    report_call((char const *const *)argv);
+    report_call(path, (char const *const *)argv,
+                (char const *const *)envp);
     return call_exect(path, argv, envp);
 }
 #endif
@@ -257,7 +311,8 @@ int execl(const char *path, const char *arg, ...) {
     char const **argv = string_array_from_varargs(arg, &args);
     va_end(args);
 
-    report_call((char const *const *)argv);
+    report_call(path, (char const *const *)argv,
+                (char const *const *)environ);
     int const result = call_execve(path, (char *const *)argv, environ);
 
     string_array_release(argv);
@@ -275,7 +330,8 @@ int execlp(const char *file, const char *arg, ...) {
     char const **argv = string_array_from_varargs(arg, &args);
     va_end(args);
 
-    report_call((char const *const *)argv);
+    report_call(file, (char const *const *)argv,
+                (char const *const *)environ);
     int const result = call_execvp(file, (char *const *)argv);
 
     string_array_release(argv);
@@ -295,7 +351,8 @@ int execle(const char *path, const char *arg, ...) {
     char const **envp = va_arg(args, char const **);
     va_end(args);
 
-    report_call((char const *const *)argv);
+    report_call(path, (char const *const *)argv,
+                (char const *const *)envp);
     int const result =
         call_execve(path, (char *const *)argv, (char *const *)envp);
 
@@ -309,8 +366,12 @@ int posix_spawn(pid_t *restrict pid, const char *restrict path,
                 const posix_spawn_file_actions_t *file_actions,
                 const posix_spawnattr_t *restrict attrp,
                 char *const argv[restrict], char *const envp[restrict]) {
-    report_call((char const *const *)argv);
-    return call_posix_spawn(pid, path, file_actions, attrp, argv, envp);
+    int const result =
+        call_posix_spawn(pid, path, file_actions, attrp, argv, envp);
+    // the child process id is known only after the call
+    report_process(path, (0 == result && pid) ? *pid : 0, getpid(),
+                   (char const *const *)argv, (char const *const *)envp);
+    return result;
 }
 #endif
 
@@ -319,8 +380,79 @@ int posix_spawnp(pid_t *restrict pid, const char *restrict file,
                  const posix_spawn_file_actions_t *file_actions,
                  const posix_spawnattr_t *restrict attrp,
                  char *const argv[restrict], char *const envp[restrict]) {
-    report_call((char const *const *)argv);
-    return call_posix_spawnp(pid, file, file_actions, attrp, argv, envp);
+    int const result =
+        call_posix_spawnp(pid, file, file_actions, attrp, argv, envp);
+    // the child process id is known only after the call
+    report_process(file, (0 == result && pid) ? *pid : 0, getpid(),
+                   (char const *const *)argv, (char const *const *)envp);
+    return result;
+}
+#endif
+
+#ifdef HAVE_WAIT
+pid_t wait(int *status) {
+    int child_status = 0;
+    pid_t const result = call_wait(&child_status);
+    if (0 < result) {
+        report_status(result, child_status);
+        if (status)
+            *status = child_status;
+    }
+    return result;
+}
+#endif
+
+#ifdef HAVE_WAITPID
+pid_t waitpid(pid_t pid, int *status, int options) {
+    int child_status = 0;
+    pid_t const result = call_waitpid(pid, &child_status, options);
+    // only the reaped children are reported
+    if (0 < result) {
+        report_status(result, child_status);
+        if (status)
+            *status = child_status;
+    }
+    return result;
+}
+#endif
+
+#ifdef HAVE_WAIT3
+pid_t wait3(int *status, int options, struct rusage *rusage) {
+    int child_status = 0;
+    pid_t const result = call_wait3(&child_status, options, rusage);
+    if (0 < result) {
+        report_status(result, child_status);
+        if (status)
+            *status = child_status;
+    }
+    return result;
+}
+#endif
+
+#ifdef HAVE_WAIT4
+pid_t wait4(pid_t pid, int *status, int options, struct rusage *rusage) {
+    int child_status = 0;
+    pid_t const result = call_wait4(pid, &child_status, options, rusage);
+    if (0 < result) {
+        report_status(result, child_status);
+        if (status)
+            *status = child_status;
+    }
+    return result;
+}
+#endif
+
+#ifdef HAVE_WAITID
+int waitid(idtype_t idtype, id_t id, siginfo_t *info, int options) {
+    int const result = call_waitid(idtype, id, info, options);
+    // the child stays waitable with WNOWAIT, it will be reported later
+    if (0 == result && info && 0 < info->si_pid && !(options & WNOWAIT)) {
+        if (CLD_EXITED == info->si_code)
+            report_exit_code(info->si_pid, info->si_status);
+        else if (CLD_KILLED == info->si_code || CLD_DUMPED == info->si_code)
+            report_exit_code(info->si_pid, 128 + info->si_status);
+    }
+    return result;
 }
 #endif
 
@@ -446,9 +578,67 @@ static int call_posix_spawnp(pid_t *restrict pid, const char *restrict file,
 }
 #endif
 
+#ifdef HAVE_WAIT
+static pid_t call_wait(int *status) {
+    typedef pid_t (*func)(int *);
+
+    DLSYM(func, fp, "wait");
+
+    return (*fp)(status);
+}
+#endif
+
+#ifdef HAVE_WAITPID
+static pid_t call_waitpid(pid_t pid, int *status, int options) {
+    typedef pid_t (*func)(pid_t, int *, int);
+
+    DLSYM(func, fp, "waitpid");
+
+    return (*fp)(pid, status, options);
+}
+#endif
+
+#ifdef HAVE_WAIT3
+static pid_t call_wait3(int *status, int options, struct rusage *rusage) {
+    typedef pid_t (*func)(int *, int, struct rusage *);
+
+    DLSYM(func, fp, "wait3");
+
+    return (*fp)(status, options, rusage);
+}
+#endif
+
+#ifdef HAVE_WAIT4
+static pid_t call_wait4(pid_t pid, int *status, int options,
+                        struct rusage *rusage) {
+    typedef pid_t (*func)(pid_t, int *, int, struct rusage *);
+
+    DLSYM(func, fp, "wait4");
+
+    return (*fp)(pid, status, options, rusage);
+}
+#endif
+
+#ifdef HAVE_WAITID
+static int call_waitid(idtype_t idtype, id_t id, siginfo_t *info, int options) {
+    typedef int (*func)(idtype_t, id_t, siginfo_t *, int);
+
+    DLSYM(func, fp, "waitid");
+
+    return (*fp)(idtype, id, info, options);
+}
+#endif
+
 /* this method is to write log about the process creation. */
 
-static void report_call(char const *const argv[]) {
+static void report_call(char const *path, char const *const argv[],
+                        char const *const envp[]) {
+    // the exec calls keep the process id
+    report_process(path, getpid(), getppid(), argv, envp);
+}
+
+static void report_process(char const *path, pid_t pid, pid_t ppid,
+                           char const *const argv[], char const *const envp[]) {
     if (!initialized)
         return;
     // Create report file name
@@ -464,7 +654,7 @@ static void report_call(char const *const argv[]) {
     if (-1 == fd)
         ERROR_AND_EXIT("mkstemp");
     // Write report file
-    const int finished = write_report(fd, argv);
+    const int finished = write_report(fd, path, pid, ppid, argv, envp, &ts);
     // Close report file
     if (close(fd))
         ERROR_AND_EXIT("close");
@@ -473,6 +663,82 @@ static void report_call(char const *const argv[]) {
         ERROR_AND_EXIT("unlink");
 }
 
+/* this method is to write log about the process termination. */
+
+static void report_exit(void) {
+    if (!report_exit_enabled)
+        return;
+    // Create report file name
+    char const * const out_dir = initial_env[0];
+    if (0 == out_dir)
+        return;
+    size_t const path_max_length = strlen(out_dir) + 64;
+    char filename[path_max_length];
+    struct timespec ts = {};
+    (void)clock_gettime(CLOCK_MONOTONIC, &ts);
+    if (-1 == snprintf(filename, path_max_length, "%s/exit.%09jd.%09jd.XXXXXX", out_dir, (intmax_t)ts.tv_sec, (intmax_t)ts.tv_nsec)) {
+        PERROR("snprintf");
+        return;
+    }
+    // Create report file (the process is about to exit, so no exit here)
+    int fd = mkstemp((char *)&filename);
+    if (-1 == fd) {
+        PERROR("mkstemp");
+        return;
+    }
+    // Write report file
+    const int finished =
+        (-1 == write_binary_pid(fd, getpid())) ||
+        (-1 == write_binary_time(fd, &ts)) ? -1 : 0;
+    // Close report file
+    if (close(fd))
+        PERROR("close");
+    // Remove the file if it's not done
+    if ((-1 == finished) && (-1 == unlink(filename)))
+        PERROR("unlink");
+}
+
+/* this method is to write log about the exit status of a child process. */
+
+static void report_status(pid_t pid, int status) {
+    // only the terminated processes have exit code (signals as the shells do)
+    if (WIFEXITED(status))
+        report_exit_code(pid, WEXITSTATUS(status));
+    else if (WIFSIGNALED(status))
+        report_exit_code(pid, 128 + WTERMSIG(status));
+}
+
+static void report_exit_code(pid_t pid, int exit_code) {
+    if (!initialized || !report_status_enabled)
+        return;
+    // Create report file name
+    char const * const out_dir = initial_env[0];
+    size_t const path_max_length = strlen(out_dir) + 64;
+    char filename[path_max_length];
+    struct timespec ts = {};
+    (void)clock_gettime(CLOCK_MONOTONIC, &ts);
+    if (-1 == snprintf(filename, path_max_length, "%s/status.%09jd.%09jd.XXXXXX", out_dir, (intmax_t)ts.tv_sec, (intmax_t)ts.tv_nsec)) {
+        PERROR("snprintf");
+        return;
+    }
+    // Create report file (the caller process shall not fail for this)
+    int fd = mkstemp((char *)&filename);
+    if (-1 == fd) {
+        PERROR("mkstemp");
+        return;
+    }
+    // Write report file
+    const int finished =
+        (-1 == write_binary_pid(fd, pid)) ||
+        (-1 == write_binary_pid(fd, exit_code)) ? -1 : 0;
+    // Close report file
+    if (close(fd))
+        PERROR("close");
+    // Remove the file if it's not done
+    if ((-1 == finished) && (-1 == unlink(filename)))
+        PERROR("unlink");
+}
+
 static int write_binary_string(int fd, const char *const string) {
     // write type
     if (-1 == write(fd, "str", 3)) {
@@ -516,7 +782,27 @@ static int write_binary_string_list(int fd, const char *const *const strings) {
     return 0;
 }
 
-static int write_report(int fd, char const *const argv[]) {
+static int write_binary_pid(int fd, pid_t pid) {
+    char buffer[32];
+    if (-1 == snprintf(buffer, sizeof(buffer), "%jd", (intmax_t)pid)) {
+        PERROR("snprintf");
+        return -1;
+    }
+    return write_binary_string(fd, buffer);
+}
+
+static int write_binary_time(int fd, struct timespec const *ts) {
+    char buffer[64];
+    if (-1 == snprintf(buffer, sizeof(buffer), "%jd.%09ld", (intmax_t)ts->tv_sec, (long)ts->tv_nsec)) {
+        PERROR("snprintf");
+        return -1;
+    }
+    return write_binary_string(fd, buffer);
+}
+
+static int write_report(int fd, char const *path, pid_t pid, pid_t ppid,
+                        char const *const argv[], char const *const envp[],
+                        struct timespec const *ts) {
     const char *cwd = getcwd(NULL, 0);
     if (0 == cwd) {
         PERROR("getcwd");
@@ -532,6 +818,34 @@ static int write_report(int fd, char const *const argv[]) {
         PERROR("cmd writing failed");
         return -1;
     }
+    if ((-1 == write_binary_pid(fd, pid)) || (-1 == write_binary_pid(fd, ppid))) {
+        PERROR("pid writing failed");
+        return -1;
+    }
+    // the executed file, argv[0] is not necessary the same
+    if (-1 == write_binary_string(fd, (path) ? path : "")) {
+        PERROR("path writing failed");
+        return -1;
+    }
+    // the requested variables from the environment of the caller (not the
+    // one visible for the child), the others might hold secrets
+    char const **const selected = string_array_select(envp, report_env_names);
+    int const env_written = write_binary_string_list(fd, selected);
+    free((void *)selected);
+    if (-1 == env_written) {
+        PERROR("env writing failed");
+        return -1;
+    }
+    // how the call was captured
+    if (-1 == write_binary_string(fd, INTERCEPTION_METHOD)) {
+        PERROR("method writing failed");
+        return -1;
+    }
+    // when the call was made
+    if (-1 == write_binary_time(fd, ts)) {
+        PERROR("time writing failed");
+        return -1;
+    }
     return 0;
 }
 
@@ -568,6 +882,13 @@ static char const **string_array_partial_update(char *const envp[], bear_env_t *
     char const **result = string_array_copy((char const **)envp);
     for (size_t it = 0; it < ENV_SIZE && (*env)[it]; ++it)
         result = string_array_single_update(result, env_names[it], (*env)[it]);
+    // keep the optional reports for the children too
+    if (report_status_enabled)
+        result = string_array_single_update(result, ENV_REPORT_STATUS, "1");
+    if (report_exit_enabled)
+        result = string_array_single_update(result, ENV_REPORT_EXIT, "1");
+    if (report_env_names)
+        result = string_array_single_update(result, ENV_REPORT_ENV, report_env_names);
     return result;
 }
 
@@ -645,6 +966,34 @@ static char const **string_array_copy(char const **const in) {
     return result;
 }
 
+/* select the entries of the environment, which name is in the colon
+ * separated list of names. the result shares the entries with the input. */
+
+static char const **string_array_select(char const *const *const in, char const *const names) {
+    size_t const size = (in && names) ? string_array_length(in) : 0;
+    char const **result = malloc((size + 1) * sizeof(char const *));
+    if (0 == result)
+        ERROR_AND_EXIT("malloc");
+    size_t count = 0;
+    for (size_t it = 0; it < size; ++it) {
+        char const * const separator = strchr(in[it], '=');
+        if (0 == separator)
+            continue;
+        size_t const key_length = (size_t)(separator - in[it]);
+        for (char const *name = names; name && *name; ) {
+            char const * const end = strchr(name, ':');
+            size_t const name_length = end ? (size_t)(end - name) : strlen(name);
+            if ((name_length == key_length) && (0 == strncmp(name, in[it], key_length))) {
+                result[count++] = in[it];
+                break;
+            }
+            name = end ? end + 1 : 0;
+        }
+    }
+    result[count] = 0;
+    return result;
+}
+
 static size_t string_array_length(char const *const *const in) {
     size_t result = 0;
     for (char const *const *it = in; (it) && (*it); ++it)